@@ -35,22 +35,73 @@
 //! ```
 
 use std::{
+    collections::HashSet,
     future::Future,
     pin::Pin,
+    sync::Arc,
     task::{Context, Poll},
 };
 
 use futures_util::ready;
-use http::{header::HeaderMap, Request, Response};
+use http::{
+    header::{HeaderMap, HeaderName, HeaderValue, CONTENT_TYPE},
+    Request, Response, StatusCode,
+};
 use pin_project::pin_project;
 use tower_layer::Layer;
 use tower_service::Service;
 
+/// Controls whether default headers overwrite headers already present on the response.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Mode {
+    /// Only set a default header if the response doesn't already have one with that name.
+    SetIfMissing,
+    /// Always set the default header, replacing any value the handler set.
+    Override,
+}
+impl Default for Mode {
+    fn default() -> Self {
+        Self::SetIfMissing
+    }
+}
+
+/// A predicate over a response's [`StatusCode`], used to select a set of headers registered
+/// with [`DefaultHeadersLayer::add_for_status`].
+type StatusPredicate = Arc<dyn Fn(StatusCode) -> bool + Send + Sync>;
+
+fn apply_headers(mode: Mode, source: &HeaderMap, headers: &mut HeaderMap) {
+    apply_headers_except(mode, source, headers, None);
+}
+
+/// Like [`apply_headers`], but skips any name present in `skip`. Used so a matching
+/// [`DefaultHeadersLayer::add_for_status`] rule always takes precedence over the general
+/// default headers for the names it sets, regardless of [`Mode`].
+fn apply_headers_except(
+    mode: Mode,
+    source: &HeaderMap,
+    headers: &mut HeaderMap,
+    skip: Option<&HashSet<HeaderName>>,
+) {
+    for (name, value) in source.iter() {
+        if let Some(skip) = skip {
+            if skip.contains(name) {
+                continue;
+            }
+        }
+        if mode == Mode::Override || !headers.contains_key(name) {
+            headers.insert(name, value.clone());
+        }
+    }
+}
+
 #[doc(hidden)]
 #[pin_project]
 pub struct ResponseFuture<F> {
-    #[pin]
-    default_headers: HeaderMap,
+    default_headers: Arc<HeaderMap>,
+    mode: Mode,
+    propagated_headers: Vec<(HeaderName, HeaderValue)>,
+    content_type: Option<HeaderValue>,
+    status_rules: Arc<Vec<(StatusPredicate, HeaderMap)>>,
     #[pin]
     future: F,
 }
@@ -63,11 +114,45 @@ where
     fn poll(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Self::Output> {
         let this = self.project();
         let mut res = ready!(this.future.poll(cx)?);
+        let status = res.status();
         let headers = res.headers_mut();
 
-        for (name, value) in this.default_headers.iter() {
-            if !headers.contains_key(name) {
-                headers.insert(name, value.clone());
+        // Status-specific rules are more specific than the general default headers, so their
+        // names are shielded from the general defaults below, rather than letting the general
+        // defaults fill the name in first and starve the rule of anything to match against. The
+        // rules themselves are still applied below, in the same relative order as before, so
+        // their interaction with propagated headers is unchanged.
+        let matching_status_rules: Vec<&HeaderMap> = this
+            .status_rules
+            .iter()
+            .filter(|(predicate, _)| predicate(status))
+            .map(|(_, rule_headers)| rule_headers)
+            .collect();
+        let status_rule_names: HashSet<HeaderName> = matching_status_rules
+            .iter()
+            .flat_map(|rule_headers| rule_headers.keys().cloned())
+            .collect();
+
+        apply_headers_except(
+            *this.mode,
+            this.default_headers,
+            headers,
+            Some(&status_rule_names),
+        );
+
+        for (name, value) in this.propagated_headers.drain(..) {
+            if !headers.contains_key(&name) {
+                headers.insert(name, value);
+            }
+        }
+
+        for rule_headers in matching_status_rules {
+            apply_headers(*this.mode, rule_headers, headers);
+        }
+
+        if let Some(content_type) = this.content_type.take() {
+            if !headers.contains_key(CONTENT_TYPE) {
+                headers.insert(CONTENT_TYPE, content_type);
             }
         }
 
@@ -78,7 +163,11 @@ where
 #[doc(hidden)]
 #[derive(Clone)]
 pub struct DefaultHeaders<S> {
-    default_headers: HeaderMap,
+    default_headers: Arc<HeaderMap>,
+    mode: Mode,
+    propagate_headers: Arc<Vec<HeaderName>>,
+    content_type: Option<HeaderValue>,
+    status_rules: Arc<Vec<(StatusPredicate, HeaderMap)>>,
     inner: S,
 }
 impl<S> DefaultHeaders<S> {}
@@ -95,9 +184,22 @@ where
     }
 
     fn call(&mut self, req: Request<RequestBody>) -> Self::Future {
+        let propagated_headers = self
+            .propagate_headers
+            .iter()
+            .filter_map(|name| {
+                req.headers()
+                    .get(name)
+                    .map(|value| (name.clone(), value.clone()))
+            })
+            .collect();
+
         ResponseFuture {
-            // TODO: juggle lifetimes and pass this in as a borrow
             default_headers: self.default_headers.clone(),
+            mode: self.mode,
+            propagated_headers,
+            content_type: self.content_type.clone(),
+            status_rules: self.status_rules.clone(),
             future: self.inner.call(req),
         }
     }
@@ -106,7 +208,11 @@ where
 /// middleware to set default HTTP response headers
 #[derive(Clone)]
 pub struct DefaultHeadersLayer {
-    default_headers: HeaderMap,
+    default_headers: Arc<HeaderMap>,
+    mode: Mode,
+    propagate_headers: Arc<Vec<HeaderName>>,
+    content_type: Option<HeaderValue>,
+    status_rules: Arc<Vec<(StatusPredicate, HeaderMap)>>,
 }
 impl DefaultHeadersLayer {
     /// Example
@@ -122,7 +228,143 @@ impl DefaultHeadersLayer {
     /// # }
     /// ```
     pub fn new(default_headers: HeaderMap) -> Self {
-        Self { default_headers }
+        Self {
+            default_headers: Arc::new(default_headers),
+            mode: Mode::default(),
+            propagate_headers: Arc::new(Vec::new()),
+            content_type: None,
+            status_rules: Arc::new(Vec::new()),
+        }
+    }
+
+    /// Add a single default header, returning `Self` so calls can be chained.
+    ///
+    /// `header` accepts anything that can be fallibly converted into a
+    /// [`HeaderName`] / [`HeaderValue`] pair, e.g. `(&str, &str)` or
+    /// `(HeaderName, HeaderValue)`.
+    ///
+    /// Example
+    /// ```
+    /// use tower_default_headers::DefaultHeadersLayer;
+    ///
+    /// let layer = DefaultHeadersLayer::default()
+    ///     .add(("x-frame-options", "deny"))
+    ///     .add(("x-content-type-options", "nosniff"));
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// Panics if `name` or `value` cannot be converted into a [`HeaderName`] /
+    /// [`HeaderValue`] respectively, e.g. because the string contains invalid
+    /// characters.
+    pub fn add<K, V>(mut self, header: (K, V)) -> Self
+    where
+        HeaderName: TryFrom<K>,
+        <HeaderName as TryFrom<K>>::Error: std::fmt::Debug,
+        HeaderValue: TryFrom<V>,
+        <HeaderValue as TryFrom<V>>::Error: std::fmt::Debug,
+    {
+        let (name, value) = header;
+        let name = HeaderName::try_from(name).expect("invalid header name");
+        let value = HeaderValue::try_from(value).expect("invalid header value");
+        Arc::make_mut(&mut self.default_headers).insert(name, value);
+        self
+    }
+
+    /// Make this layer overwrite headers the handler already set, instead of only filling in
+    /// ones that are missing.
+    ///
+    /// Example
+    /// ```
+    /// use tower_default_headers::DefaultHeadersLayer;
+    ///
+    /// let layer = DefaultHeadersLayer::default()
+    ///     .add(("x-frame-options", "deny"))
+    ///     .override_existing();
+    /// ```
+    pub fn override_existing(mut self) -> Self {
+        self.mode = Mode::Override;
+        self
+    }
+
+    /// Copy the named header from the incoming request onto the outgoing response, if the
+    /// response doesn't already have one with that name.
+    ///
+    /// This is useful for echoing correlation IDs (e.g. `x-request-id`) from the request onto
+    /// every response without per-handler code.
+    ///
+    /// Example
+    /// ```
+    /// use http::header::HeaderName;
+    /// use tower_default_headers::DefaultHeadersLayer;
+    ///
+    /// let layer = DefaultHeadersLayer::default()
+    ///     .propagate(HeaderName::from_static("x-request-id"));
+    /// ```
+    pub fn propagate(mut self, name: HeaderName) -> Self {
+        Arc::make_mut(&mut self.propagate_headers).push(name);
+        self
+    }
+
+    /// Set a fallback `Content-Type` to apply to responses that don't already have one.
+    ///
+    /// Unlike the headers added with [`DefaultHeadersLayer::add`], this is only ever inserted
+    /// when the response is missing a `Content-Type`, regardless of [`Mode`]. The response's
+    /// status and body are not inspected, so an empty or `204 No Content` response without a
+    /// `Content-Type` receives it as well.
+    ///
+    /// Example
+    /// ```
+    /// use http::HeaderValue;
+    /// use tower_default_headers::DefaultHeadersLayer;
+    ///
+    /// let layer = DefaultHeadersLayer::default()
+    ///     .content_type(HeaderValue::from_static("text/plain"));
+    /// ```
+    pub fn content_type(mut self, content_type: HeaderValue) -> Self {
+        self.content_type = Some(content_type);
+        self
+    }
+
+    /// Convenience for [`DefaultHeadersLayer::content_type`] with `text/html; charset=utf-8`.
+    pub fn content_type_html(self) -> Self {
+        self.content_type(HeaderValue::from_static("text/html; charset=utf-8"))
+    }
+
+    /// Register a set of headers that only apply to responses whose [`StatusCode`] matches
+    /// `predicate`, e.g. only setting `Cache-Control` on error responses.
+    ///
+    /// Rules still respect the configured [`Mode`]: under the default [`Mode::SetIfMissing`] a
+    /// matching rule only fills in headers the response doesn't already have, while
+    /// [`Mode::Override`] always replaces them.
+    ///
+    /// Example
+    /// ```
+    /// use http::{
+    ///     header::{HeaderMap, HeaderValue, CACHE_CONTROL},
+    ///     StatusCode,
+    /// };
+    /// use tower_default_headers::DefaultHeadersLayer;
+    ///
+    /// let mut no_store = HeaderMap::new();
+    /// no_store.insert(CACHE_CONTROL, HeaderValue::from_static("no-store"));
+    ///
+    /// let layer = DefaultHeadersLayer::default()
+    ///     .add_for_status(|status| status.is_server_error(), no_store);
+    /// ```
+    pub fn add_for_status<P>(mut self, predicate: P, headers: HeaderMap) -> Self
+    where
+        P: Fn(StatusCode) -> bool + Send + Sync + 'static,
+    {
+        Arc::make_mut(&mut self.status_rules).push((Arc::new(predicate), headers));
+        self
+    }
+}
+impl Default for DefaultHeadersLayer {
+    /// Create a layer with no default headers set, ready to be built up with
+    /// [`DefaultHeadersLayer::add`].
+    fn default() -> Self {
+        Self::new(HeaderMap::new())
     }
 }
 impl<S> Layer<S> for DefaultHeadersLayer {
@@ -130,8 +372,11 @@ impl<S> Layer<S> for DefaultHeadersLayer {
 
     fn layer(&self, inner: S) -> Self::Service {
         Self::Service {
-            // TODO: juggle lifetimes and pass this in as a borrow
             default_headers: self.default_headers.clone(),
+            mode: self.mode,
+            propagate_headers: self.propagate_headers.clone(),
+            content_type: self.content_type.clone(),
+            status_rules: self.status_rules.clone(),
             inner,
         }
     }
@@ -142,7 +387,7 @@ mod tests {
     use axum::{
         body::Body,
         http::{
-            header::{HeaderValue, X_FRAME_OPTIONS},
+            header::{HeaderName, HeaderValue, X_FRAME_OPTIONS},
             Request, StatusCode,
         },
         routing::{get, Router},
@@ -174,6 +419,230 @@ mod tests {
         assert_eq!(&body[..], b"hello, world!");
     }
 
+    #[tokio::test]
+    async fn test_builder_add() {
+        let app = Router::new()
+            .route("/", get(|| async { "hello, world!" }))
+            .layer(
+                DefaultHeadersLayer::default()
+                    .add(("x-frame-options", "deny"))
+                    .add(("x-content-type-options", "nosniff")),
+            );
+
+        let response = app
+            .oneshot(Request::builder().uri("/").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let headers = response.headers();
+        assert_eq!(headers["x-frame-options"], "deny");
+        assert_eq!(headers["x-content-type-options"], "nosniff");
+    }
+
+    #[tokio::test]
+    async fn test_override_existing() {
+        let mut default_headers = HeaderMap::new();
+        default_headers.insert(X_FRAME_OPTIONS, HeaderValue::from_static("deny"));
+
+        let app = Router::new()
+            .route(
+                "/",
+                get(|| async {
+                    let mut headers = HeaderMap::new();
+                    headers.insert("x-frame-options", HeaderValue::from_static("sameorigin"));
+                    (headers, "hello, world!")
+                }),
+            )
+            .layer(DefaultHeadersLayer::new(default_headers).override_existing());
+
+        let response = app
+            .oneshot(Request::builder().uri("/").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let headers = response.headers();
+        assert_eq!(headers["x-frame-options"], "deny");
+    }
+
+    #[tokio::test]
+    async fn test_propagate() {
+        let app = Router::new()
+            .route("/", get(|| async { "hello, world!" }))
+            .layer(
+                DefaultHeadersLayer::default().propagate(HeaderName::from_static("x-request-id")),
+            );
+
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .uri("/")
+                    .header("x-request-id", "abc123")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let headers = response.headers();
+        assert_eq!(headers["x-request-id"], "abc123");
+    }
+
+    #[tokio::test]
+    async fn test_content_type_fallback() {
+        let app = Router::new()
+            .route(
+                "/",
+                get(|| async { Response::new(Body::from("hello, world!")) }),
+            )
+            .layer(DefaultHeadersLayer::default().content_type_html());
+
+        let response = app
+            .oneshot(Request::builder().uri("/").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let headers = response.headers();
+        assert_eq!(headers["content-type"], "text/html; charset=utf-8");
+    }
+
+    #[tokio::test]
+    async fn test_content_type_not_applied_when_already_set() {
+        let app = Router::new()
+            .route(
+                "/",
+                get(|| async {
+                    let mut headers = HeaderMap::new();
+                    headers.insert("content-type", HeaderValue::from_static("application/json"));
+                    (headers, "{}")
+                }),
+            )
+            .layer(DefaultHeadersLayer::default().content_type_html());
+
+        let response = app
+            .oneshot(Request::builder().uri("/").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let headers = response.headers();
+        assert_eq!(headers["content-type"], "application/json");
+    }
+
+    #[tokio::test]
+    async fn test_add_for_status() {
+        let mut no_store = HeaderMap::new();
+        no_store.insert("cache-control", HeaderValue::from_static("no-store"));
+
+        let app = Router::new()
+            .route("/ok", get(|| async { "hello, world!" }))
+            .route(
+                "/error",
+                get(|| async { (StatusCode::INTERNAL_SERVER_ERROR, "oops") }),
+            )
+            .layer(
+                DefaultHeadersLayer::default()
+                    .add_for_status(|status| status.is_server_error(), no_store),
+            );
+
+        let ok_response = app
+            .clone()
+            .oneshot(Request::builder().uri("/ok").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        assert!(!ok_response.headers().contains_key("cache-control"));
+
+        let error_response = app
+            .oneshot(
+                Request::builder()
+                    .uri("/error")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(error_response.status(), StatusCode::INTERNAL_SERVER_ERROR);
+        assert_eq!(error_response.headers()["cache-control"], "no-store");
+    }
+
+    #[tokio::test]
+    async fn test_add_for_status_overrides_general_default_of_same_name() {
+        let mut default_headers = HeaderMap::new();
+        default_headers.insert("cache-control", HeaderValue::from_static("max-age=3600"));
+
+        let mut no_store = HeaderMap::new();
+        no_store.insert("cache-control", HeaderValue::from_static("no-store"));
+
+        let app = Router::new()
+            .route("/ok", get(|| async { "hello, world!" }))
+            .route(
+                "/error",
+                get(|| async { (StatusCode::INTERNAL_SERVER_ERROR, "oops") }),
+            )
+            .layer(
+                DefaultHeadersLayer::new(default_headers)
+                    .add_for_status(|status| status.is_server_error(), no_store),
+            );
+
+        let ok_response = app
+            .clone()
+            .oneshot(Request::builder().uri("/ok").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(ok_response.headers()["cache-control"], "max-age=3600");
+
+        let error_response = app
+            .oneshot(
+                Request::builder()
+                    .uri("/error")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(error_response.status(), StatusCode::INTERNAL_SERVER_ERROR);
+        assert_eq!(error_response.headers()["cache-control"], "no-store");
+    }
+
+    #[tokio::test]
+    async fn test_propagated_header_wins_over_status_rule_of_same_name() {
+        let mut rule_headers = HeaderMap::new();
+        rule_headers.insert("x-request-id", HeaderValue::from_static("fallback"));
+
+        let app = Router::new()
+            .route(
+                "/error",
+                get(|| async { (StatusCode::INTERNAL_SERVER_ERROR, "oops") }),
+            )
+            .layer(
+                DefaultHeadersLayer::default()
+                    .propagate(HeaderName::from_static("x-request-id"))
+                    .add_for_status(|status| status.is_server_error(), rule_headers),
+            );
+
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .uri("/error")
+                    .header("x-request-id", "abc123")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::INTERNAL_SERVER_ERROR);
+        assert_eq!(response.headers()["x-request-id"], "abc123");
+    }
+
     #[tokio::test]
     async fn test_headers_when_already_set_by_handler() {
         let mut default_headers = HeaderMap::new();